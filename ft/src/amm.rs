@@ -0,0 +1,381 @@
+//! A single built-in constant-product `token`/NEAR pool, so this token can bootstrap trading
+//! without a third-party DEX.
+//!
+//! Adding liquidity is two steps, because only a `ft_transfer_call` can move the token leg while
+//! only a payable call can attach the NEAR leg: a holder first sends the token side via
+//! `ft_transfer_call(current_account_id, amount, "add_liquidity")`, which lands in `ft_on_transfer`
+//! below and is parked in `amm_pending_token_deposits`; they then call `add_liquidity` with the
+//! NEAR side attached, which consumes the pending deposit and mints LP shares for both sides at
+//! once. Swapping NEAR for tokens is a single payable call; swapping tokens for NEAR goes through
+//! `ft_transfer_call` with msg `"swap:<min_out>"`, since that's how tokens enter the contract.
+
+use near_contract_standards::fungible_token::receiver::FungibleTokenReceiver;
+use near_sdk::json_types::U128;
+use near_sdk::{
+    assert_one_yocto, env, log, near_bindgen, require, AccountId, Balance, Promise, PromiseOrValue,
+};
+
+use crate::math::{mul_div, sqrt_mul};
+use crate::Contract;
+
+const BASIS_POINTS_DENOMINATOR: u16 = 10_000;
+/// LP shares permanently locked on the first deposit, so the first liquidity provider can't mint
+/// a dust amount of shares and later inflate their value by donating reserves directly.
+const MINIMUM_LIQUIDITY: Balance = 1_000;
+
+impl Contract {
+    fn internal_swap_token_for_near(
+        &mut self,
+        sender_id: &AccountId,
+        amount_in: Balance,
+        min_out: Balance,
+    ) {
+        require!(
+            self.amm_reserve_token > 0 && self.amm_reserve_near > 0,
+            "Pool has no liquidity"
+        );
+        let in_net = mul_div(
+            amount_in,
+            Balance::from(BASIS_POINTS_DENOMINATOR - self.amm_fee_bps),
+            Balance::from(BASIS_POINTS_DENOMINATOR),
+        );
+        let out = mul_div(self.amm_reserve_near, in_net, self.amm_reserve_token + in_net);
+        require!(out >= min_out, "Slippage: output below min_out");
+
+        self.amm_reserve_token += amount_in;
+        self.amm_reserve_near -= out;
+        Promise::new(sender_id.clone()).transfer(out);
+        log!(
+            "AMM swap: {} token -> {} yoctoNEAR for @{}",
+            amount_in,
+            out,
+            sender_id
+        );
+    }
+}
+
+#[near_bindgen]
+impl FungibleTokenReceiver for Contract {
+    /// Routes tokens arriving via `ft_transfer_call` into the AMM based on `msg`:
+    /// `"add_liquidity"` parks them for the next `add_liquidity` call, `"swap:<min_out>"` swaps
+    /// them for NEAR immediately. Always consumes the full `amount` (returns 0 to refund) on
+    /// success; panicking triggers the standard NEP-141 refund of the whole transfer.
+    fn ft_on_transfer(
+        &mut self,
+        sender_id: AccountId,
+        amount: U128,
+        msg: String,
+    ) -> PromiseOrValue<U128> {
+        let amount: Balance = amount.into();
+        if msg == "add_liquidity" {
+            let pending = self.amm_pending_token_deposits.get(&sender_id).unwrap_or(0);
+            self.amm_pending_token_deposits
+                .insert(&sender_id, &(pending + amount));
+        } else if let Some(min_out) = msg.strip_prefix("swap:") {
+            let min_out: Balance = min_out
+                .parse()
+                .unwrap_or_else(|_| env::panic_str("Invalid min_out"));
+            self.internal_swap_token_for_near(&sender_id, amount, min_out);
+        } else {
+            env::panic_str("Unknown msg: expected \"add_liquidity\" or \"swap:<min_out>\"");
+        }
+        PromiseOrValue::Value(0.into())
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Owner-only: sets the AMM swap fee, in basis points.
+    #[payable]
+    pub fn set_amm_fee_bps(&mut self, amm_fee_bps: u16) {
+        assert_one_yocto();
+        require!(
+            env::predecessor_account_id() == self.owner_id,
+            "Only the owner can set the AMM fee"
+        );
+        require!(
+            amm_fee_bps < BASIS_POINTS_DENOMINATOR,
+            "Fee must be below 10000 bps"
+        );
+        self.amm_fee_bps = amm_fee_bps;
+    }
+
+    /// Finalizes an `add_liquidity` deposit: consumes the token amount parked by a prior
+    /// `ft_transfer_call(current_account_id, amount, "add_liquidity")` from the caller, pairs it
+    /// with the attached NEAR deposit, and mints LP shares proportional to the pool (or, for the
+    /// first deposit, `sqrt(token_amount * near_amount)` minus the permanently-locked minimum).
+    #[payable]
+    pub fn add_liquidity(&mut self) -> U128 {
+        let initial_storage_usage = env::storage_usage();
+        let account_id = env::predecessor_account_id();
+        let near_amount = env::attached_deposit();
+        require!(near_amount > 0, "Must attach NEAR to add liquidity");
+        let token_amount = self
+            .amm_pending_token_deposits
+            .remove(&account_id)
+            .unwrap_or(0);
+        require!(
+            token_amount > 0,
+            "No pending token deposit; ft_transfer_call with msg \"add_liquidity\" first"
+        );
+
+        let minted_shares = if self.amm_total_lp_shares == 0 {
+            let initial_shares = sqrt_mul(token_amount, near_amount);
+            require!(
+                initial_shares > MINIMUM_LIQUIDITY,
+                "Initial liquidity too small"
+            );
+            self.amm_total_lp_shares = MINIMUM_LIQUIDITY;
+            initial_shares - MINIMUM_LIQUIDITY
+        } else {
+            std::cmp::min(
+                mul_div(token_amount, self.amm_total_lp_shares, self.amm_reserve_token),
+                mul_div(near_amount, self.amm_total_lp_shares, self.amm_reserve_near),
+            )
+        };
+        require!(minted_shares > 0, "Insufficient liquidity minted");
+
+        self.amm_reserve_token += token_amount;
+        self.amm_reserve_near += near_amount;
+        self.amm_total_lp_shares += minted_shares;
+        let shares = self.amm_lp_shares.get(&account_id).unwrap_or(0) + minted_shares;
+        self.amm_lp_shares.insert(&account_id, &shares);
+
+        log!(
+            "@{} added {} token + {} yoctoNEAR liquidity for {} shares",
+            account_id,
+            token_amount,
+            near_amount,
+            minted_shares
+        );
+        self.internal_charge_storage_growth(initial_storage_usage);
+        minted_shares.into()
+    }
+
+    /// Burns `shares` of the caller's LP units, returning their proportional share of both
+    /// reserves: the token side is credited to the caller's FT balance, the NEAR side is
+    /// transferred directly.
+    #[payable]
+    pub fn remove_liquidity(&mut self, shares: U128) -> (U128, U128) {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let shares: Balance = shares.into();
+        let holder_shares = self.amm_lp_shares.get(&account_id).unwrap_or(0);
+        require!(
+            shares > 0 && shares <= holder_shares,
+            "Insufficient LP shares"
+        );
+
+        let token_out = mul_div(self.amm_reserve_token, shares, self.amm_total_lp_shares);
+        let near_out = mul_div(self.amm_reserve_near, shares, self.amm_total_lp_shares);
+        require!(
+            token_out > 0 && near_out > 0,
+            "Share amount too small to redeem"
+        );
+        self.internal_require_registered(&account_id);
+
+        self.amm_lp_shares
+            .insert(&account_id, &(holder_shares - shares));
+        self.amm_total_lp_shares -= shares;
+        self.amm_reserve_token -= token_out;
+        self.amm_reserve_near -= near_out;
+
+        self.token
+            .internal_withdraw(&env::current_account_id(), token_out);
+        self.token.internal_deposit(&account_id, token_out);
+        Promise::new(account_id.clone()).transfer(near_out);
+
+        let account_balance = self.token.accounts.get(&account_id).unwrap_or(0);
+        self.internal_record_balance_checkpoint(&account_id, account_balance);
+        log!(
+            "@{} removed {} shares for {} token + {} yoctoNEAR",
+            account_id,
+            shares,
+            token_out,
+            near_out
+        );
+        (token_out.into(), near_out.into())
+    }
+
+    /// Swaps attached NEAR for tokens through the constant-product pool, crediting the caller's
+    /// FT balance. Panics if the output would be below `min_out` (slippage protection).
+    #[payable]
+    pub fn swap(&mut self, min_out: U128) -> U128 {
+        let account_id = env::predecessor_account_id();
+        let in_amount = env::attached_deposit();
+        require!(in_amount > 0, "Must attach NEAR to swap");
+        require!(
+            self.amm_reserve_token > 0 && self.amm_reserve_near > 0,
+            "Pool has no liquidity"
+        );
+        self.internal_require_registered(&account_id);
+
+        let in_net = mul_div(
+            in_amount,
+            Balance::from(BASIS_POINTS_DENOMINATOR - self.amm_fee_bps),
+            Balance::from(BASIS_POINTS_DENOMINATOR),
+        );
+        let out = mul_div(self.amm_reserve_token, in_net, self.amm_reserve_near + in_net);
+        require!(out >= min_out.into(), "Slippage: output below min_out");
+
+        self.amm_reserve_near += in_amount;
+        self.amm_reserve_token -= out;
+        self.token
+            .internal_withdraw(&env::current_account_id(), out);
+        self.token.internal_deposit(&account_id, out);
+
+        let account_balance = self.token.accounts.get(&account_id).unwrap_or(0);
+        self.internal_record_balance_checkpoint(&account_id, account_balance);
+        log!(
+            "AMM swap: {} yoctoNEAR -> {} token for @{}",
+            in_amount,
+            out,
+            account_id
+        );
+        out.into()
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_contract_standards::fungible_token::core::FungibleTokenCore;
+    use near_contract_standards::storage_management::StorageManagement;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::MockedBlockchain;
+    use near_sdk::testing_env;
+
+    use super::*;
+
+    // 24-decimal-scale amounts (roughly "1 token" / "1 NEAR"), so reserve math that multiplies two
+    // of these before dividing (e.g. `sqrt_mul`/`mul_div` in `add_liquidity`/`swap`) exercises the
+    // same magnitudes that overflow a plain `u128` multiply in production.
+    const TOTAL_SUPPLY: Balance = 10_000_000_000_000_000_000_000_000_000;
+    const TOKEN_LIQUIDITY: Balance = 1_000_000_000_000_000_000_000_000;
+    const NEAR_LIQUIDITY: Balance = 1_000_000_000_000_000_000_000_000;
+
+    fn get_context(predecessor_account_id: AccountId) -> VMContextBuilder {
+        let mut builder = VMContextBuilder::new();
+        builder
+            .current_account_id(accounts(0))
+            .signer_account_id(predecessor_account_id.clone())
+            .predecessor_account_id(predecessor_account_id);
+        builder
+    }
+
+    /// `accounts(0)` is both the contract's own account and the token owner, so it doubles as the
+    /// pool's token-side escrow without a separate registration step.
+    fn setup() -> (Contract, VMContextBuilder) {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let contract = Contract::new_default_meta(accounts(0).into(), TOTAL_SUPPLY.into());
+        (contract, context)
+    }
+
+    fn register_and_fund(
+        contract: &mut Contract,
+        context: &mut VMContextBuilder,
+        account_id: AccountId,
+        amount: Balance,
+    ) {
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(contract.storage_balance_bounds().min.into())
+            .predecessor_account_id(account_id.clone())
+            .build());
+        contract.storage_deposit(None, None);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(0))
+            .build());
+        contract.ft_transfer(account_id, amount.into(), None);
+    }
+
+    /// Parks `amount` as a pending `add_liquidity` deposit for `account_id`, the way a real
+    /// `ft_transfer_call(current_account_id, amount, "add_liquidity")` would once the token side
+    /// has landed on the contract's own balance.
+    fn deposit_for_add_liquidity(
+        contract: &mut Contract,
+        context: &mut VMContextBuilder,
+        account_id: &AccountId,
+        amount: Balance,
+    ) {
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .predecessor_account_id(account_id.clone())
+            .build());
+        contract.token.internal_withdraw(account_id, amount);
+        contract.token.internal_deposit(&accounts(0), amount);
+        let _ = contract.ft_on_transfer(account_id.clone(), amount.into(), "add_liquidity".to_string());
+    }
+
+    #[test]
+    fn test_add_liquidity_swap_and_remove_liquidity() {
+        let (mut contract, mut context) = setup();
+        register_and_fund(&mut contract, &mut context, accounts(1), TOKEN_LIQUIDITY * 2);
+
+        deposit_for_add_liquidity(&mut contract, &mut context, &accounts(1), TOKEN_LIQUIDITY);
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(NEAR_LIQUIDITY)
+            .predecessor_account_id(accounts(1))
+            .build());
+        let expected_initial_shares = sqrt_mul(TOKEN_LIQUIDITY, NEAR_LIQUIDITY) - MINIMUM_LIQUIDITY;
+        let minted = contract.add_liquidity();
+        assert_eq!(minted.0, expected_initial_shares);
+        assert_eq!(contract.amm_reserve_token, TOKEN_LIQUIDITY);
+        assert_eq!(contract.amm_reserve_near, NEAR_LIQUIDITY);
+
+        // Swap a small amount of NEAR for tokens; the reserves and the caller's balance must move
+        // by exactly the amounts in and out even though the amounts are at 24-decimal scale.
+        let swap_in: Balance = 1_000_000_000_000_000_000_000;
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(swap_in)
+            .predecessor_account_id(accounts(1))
+            .build());
+        let token_reserve_before = contract.amm_reserve_token;
+        let near_reserve_before = contract.amm_reserve_near;
+        let balance_before = contract.token.accounts.get(&accounts(1)).unwrap_or(0);
+        let out = contract.swap(0.into());
+        assert!(out.0 > 0);
+        assert_eq!(contract.amm_reserve_near, near_reserve_before + swap_in);
+        assert_eq!(contract.amm_reserve_token, token_reserve_before - out.0);
+        assert_eq!(
+            contract.token.accounts.get(&accounts(1)).unwrap_or(0),
+            balance_before + out.0
+        );
+
+        // The sole liquidity provider redeems every share it holds; it gets back its
+        // proportional share of whatever remains in the reserves (everything but the
+        // permanently-locked `MINIMUM_LIQUIDITY` share).
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(1))
+            .build());
+        let shares = contract.amm_lp_shares.get(&accounts(1)).unwrap();
+        let expected_token_out =
+            mul_div(contract.amm_reserve_token, shares, contract.amm_total_lp_shares);
+        let expected_near_out =
+            mul_div(contract.amm_reserve_near, shares, contract.amm_total_lp_shares);
+        let (token_out, near_out) = contract.remove_liquidity(shares.into());
+        assert_eq!(token_out.0, expected_token_out);
+        assert_eq!(near_out.0, expected_near_out);
+        assert_eq!(contract.amm_lp_shares.get(&accounts(1)).unwrap_or(0), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Pool has no liquidity")]
+    fn test_swap_requires_existing_liquidity() {
+        let (mut contract, mut context) = setup();
+        register_and_fund(&mut contract, &mut context, accounts(1), TOKEN_LIQUIDITY);
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1_000)
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.swap(0.into());
+    }
+}
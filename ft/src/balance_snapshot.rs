@@ -0,0 +1,165 @@
+//! Point-in-time balance and supply queries, resolved from append-only checkpoint logs.
+//!
+//! A checkpoint is written whenever an account's balance (or the total supply) changes at a new
+//! block height, so `ft_balance_of_at`/`ft_total_supply_at` can answer "what was the balance at
+//! block H" by binary-searching for the latest checkpoint at or before H, without needing an
+//! external indexer.
+
+use near_sdk::collections::Vector;
+use near_sdk::json_types::U128;
+use near_sdk::{assert_one_yocto, env, near_bindgen, require, AccountId, Balance};
+
+use crate::{Contract, StorageKey};
+
+/// `(block_height, balance)` pair recorded in a checkpoint log.
+pub(crate) type Checkpoint = (u64, Balance);
+
+/// Default checkpoint retention window, in blocks: roughly two months at NEAR's ~1-second block
+/// time. `ft_transfer`/`ft_transfer_call` write a checkpoint on every ordinary transfer and can't
+/// charge the caller for that growth the way `vest`/`stake`/`add_liquidity` do (NEP-141 fixes
+/// their signature and requires exactly 1 yoctoNEAR attached), so pruning defaults to on instead
+/// of unbounded -- otherwise every transfer would permanently grow storage the contract itself
+/// pays for. The owner can widen or disable it via `set_checkpoint_retention_blocks`.
+pub(crate) const DEFAULT_CHECKPOINT_RETENTION_BLOCKS: u64 = 5_000_000;
+
+impl Contract {
+    /// Appends a `(block_height, balance)` checkpoint for `account_id`, coalescing multiple
+    /// writes within the same block and pruning entries older than the retention window.
+    pub(crate) fn internal_record_balance_checkpoint(
+        &mut self,
+        account_id: &AccountId,
+        balance: Balance,
+    ) {
+        let height = env::block_height();
+        let mut checkpoints = self.balance_checkpoints.get(account_id).unwrap_or_else(|| {
+            Vector::new(StorageKey::BalanceCheckpointsInner {
+                account_hash: env::sha256(account_id.as_bytes()),
+            })
+        });
+        Self::internal_push_checkpoint(&mut checkpoints, height, balance);
+        self.internal_prune_checkpoints(&mut checkpoints, height);
+        self.balance_checkpoints.insert(account_id, &checkpoints);
+    }
+
+    /// Records fresh checkpoints for both legs of a transfer. Shared by `ft_transfer`,
+    /// `ft_transfer_call` and `ft_resolve_transfer` so every path that can move a balance (a
+    /// direct transfer, a successful `ft_transfer_call`, and one partially or fully refunded by
+    /// the receiver) ends up with accurate history, not just the subsystem-specific transfers.
+    pub(crate) fn internal_record_transfer_checkpoints(
+        &mut self,
+        sender_id: &AccountId,
+        receiver_id: &AccountId,
+    ) {
+        let sender_balance = self.token.accounts.get(sender_id).unwrap_or(0);
+        let receiver_balance = self.token.accounts.get(receiver_id).unwrap_or(0);
+        self.internal_record_balance_checkpoint(sender_id, sender_balance);
+        self.internal_record_balance_checkpoint(receiver_id, receiver_balance);
+    }
+
+    /// Appends a `(block_height, total_supply)` checkpoint, mirroring the per-account log.
+    pub(crate) fn internal_record_supply_checkpoint(&mut self) {
+        let height = env::block_height();
+        let total_supply = self.token.total_supply;
+        Self::internal_push_checkpoint(&mut self.supply_checkpoints, height, total_supply);
+    }
+
+    fn internal_push_checkpoint(
+        checkpoints: &mut Vector<Checkpoint>,
+        height: u64,
+        balance: Balance,
+    ) {
+        let last_index = checkpoints.len().checked_sub(1);
+        match last_index.map(|i| (i, checkpoints.get(i).unwrap())) {
+            Some((i, (last_height, _))) if last_height == height => {
+                // Coalesce same-block writes by overwriting the last entry.
+                checkpoints.replace(i, &(height, balance));
+            }
+            _ => checkpoints.push(&(height, balance)),
+        }
+    }
+
+    fn internal_prune_checkpoints(&self, checkpoints: &mut Vector<Checkpoint>, now: u64) {
+        let retention = match self.checkpoint_retention_blocks {
+            Some(retention) => retention,
+            None => return,
+        };
+        let cutoff = now.saturating_sub(retention);
+        // Keep the latest checkpoint at or before the cutoff (so reads right at the edge of the
+        // retention window still resolve to a balance instead of 0) and drop everything older.
+        let mut low: u64 = 0;
+        let mut high: u64 = checkpoints.len();
+        while low < high {
+            let mid = low + (high - low) / 2;
+            let (height, _) = checkpoints.get(mid).unwrap();
+            if height <= cutoff {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        let drop_count = low.saturating_sub(1);
+        if drop_count == 0 {
+            return;
+        }
+        // Vector has no cheap pop-front, so rebuild it in place preserving order.
+        let retained: Vec<Checkpoint> = (drop_count..checkpoints.len())
+            .map(|i| checkpoints.get(i).unwrap())
+            .collect();
+        checkpoints.clear();
+        for entry in retained {
+            checkpoints.push(&entry);
+        }
+    }
+
+    /// Binary-searches `checkpoints` for the balance recorded at the greatest
+    /// `block_height <= requested`, returning 0 if there is none.
+    fn resolve_checkpoint(checkpoints: &Vector<Checkpoint>, requested_height: u64) -> Balance {
+        let mut low: u64 = 0;
+        let mut high: u64 = checkpoints.len();
+        while low < high {
+            let mid = low + (high - low) / 2;
+            let (height, _) = checkpoints.get(mid).unwrap();
+            if height <= requested_height {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        if low == 0 {
+            0
+        } else {
+            checkpoints.get(low - 1).unwrap().1
+        }
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Returns `account_id`'s fungible token balance as of `block_height`, resolved from its
+    /// checkpoint history (0 if the account had no checkpoint at or before that height).
+    pub fn ft_balance_of_at(&self, account_id: AccountId, block_height: u64) -> U128 {
+        match self.balance_checkpoints.get(&account_id) {
+            Some(checkpoints) => Self::resolve_checkpoint(&checkpoints, block_height).into(),
+            None => 0.into(),
+        }
+    }
+
+    /// Returns the total supply as of `block_height`, resolved from the supply checkpoint log.
+    pub fn ft_total_supply_at(&self, block_height: u64) -> U128 {
+        Self::resolve_checkpoint(&self.supply_checkpoints, block_height).into()
+    }
+
+    /// Owner-only: sets the checkpoint retention window in blocks, overriding the
+    /// [`DEFAULT_CHECKPOINT_RETENTION_BLOCKS`] every contract starts with. Checkpoints older than
+    /// this many blocks are pruned lazily on the next write for the affected account. `None`
+    /// disables pruning.
+    #[payable]
+    pub fn set_checkpoint_retention_blocks(&mut self, retention_blocks: Option<u64>) {
+        assert_one_yocto();
+        require!(
+            env::predecessor_account_id() == self.owner_id,
+            "Only the owner can set the checkpoint retention window"
+        );
+        self.checkpoint_retention_blocks = retention_blocks;
+    }
+}
@@ -0,0 +1,260 @@
+//! Atomic multi-receiver transfer, for the common pattern of crediting a dozen accounts from a
+//! single call instead of one `ft_transfer` per receiver.
+
+use near_contract_standards::fungible_token::events::FtTransfer;
+use near_sdk::json_types::U128;
+use near_sdk::{env, log, near_bindgen, require, AccountId, Balance};
+
+use crate::Contract;
+
+const BASIS_POINTS_DENOMINATOR: u16 = 10_000;
+
+impl Contract {
+    /// Validates `transfers` (positive amounts, every receiver distinct from the sender and
+    /// storage-registered) and returns their summed total.
+    fn internal_validate_batch(
+        &self,
+        sender_id: &AccountId,
+        transfers: &[(AccountId, U128)],
+    ) -> Balance {
+        require!(!transfers.is_empty(), "transfers must not be empty");
+        let mut total: Balance = 0;
+        for (receiver_id, amount) in transfers {
+            let amount: Balance = (*amount).into();
+            require!(
+                amount > 0,
+                "Each transfer amount should be a positive number"
+            );
+            require!(
+                receiver_id != sender_id,
+                "Sender and receiver should be different"
+            );
+            self.internal_require_registered(receiver_id);
+            total += amount;
+        }
+        total
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Transfers to every `(receiver_id, amount)` pair in `transfers` atomically: the sender's
+    /// balance is withdrawn once for the summed total, then credited to each receiver in a loop.
+    /// Panics (reverting every leg) unless every receiver is storage-registered and the sender's
+    /// balance covers the total up front. Emits a single `FtTransfer` event listing every leg,
+    /// rather than one event per receiver. The per-receiver checkpoints this writes cost more
+    /// storage than a single transfer, so instead of the usual 1-yoctoNEAR requirement, the
+    /// attached deposit must cover that growth -- same accounting as `vest`/`stake`/`add_liquidity`.
+    #[payable]
+    pub fn ft_transfer_batch(&mut self, transfers: Vec<(AccountId, U128)>, memo: Option<String>) {
+        let initial_storage_usage = env::storage_usage();
+        let sender_id = env::predecessor_account_id();
+        let total = self.internal_validate_batch(&sender_id, &transfers);
+
+        self.token.internal_withdraw(&sender_id, total);
+        let mut legs: Vec<(&AccountId, U128)> = Vec::with_capacity(transfers.len());
+        for (receiver_id, amount) in transfers.iter() {
+            let amount: Balance = (*amount).into();
+            self.token.internal_deposit(receiver_id, amount);
+            legs.push((receiver_id, amount.into()));
+            let receiver_balance = self.token.accounts.get(receiver_id).unwrap_or(0);
+            self.internal_record_balance_checkpoint(receiver_id, receiver_balance);
+        }
+        let events: Vec<FtTransfer> = legs
+            .iter()
+            .map(|(receiver_id, amount)| FtTransfer {
+                old_owner_id: &sender_id,
+                new_owner_id: receiver_id,
+                amount,
+                memo: memo.as_deref(),
+            })
+            .collect();
+        FtTransfer::emit_many(&events);
+
+        let sender_balance = self.token.accounts.get(&sender_id).unwrap_or(0);
+        self.internal_record_balance_checkpoint(&sender_id, sender_balance);
+        log!(
+            "@{} batch transferred {} total across {} receivers",
+            sender_id,
+            total,
+            transfers.len()
+        );
+        self.internal_charge_storage_growth(initial_storage_usage);
+    }
+
+    /// Like [`ft_transfer_batch`](Self::ft_transfer_batch), but deducts `fee_bps` basis points
+    /// from each leg and routes the accumulated fee to `fee_recipient` as one extra leg of the
+    /// same atomic transfer. Emits a single `FtTransfer` event listing every leg, including the
+    /// fee leg, rather than one event per receiver. Charges the attached deposit for the storage
+    /// growth of every leg's checkpoint, same as [`ft_transfer_batch`](Self::ft_transfer_batch).
+    #[payable]
+    pub fn ft_transfer_batch_with_fee(
+        &mut self,
+        transfers: Vec<(AccountId, U128)>,
+        fee_bps: u16,
+        fee_recipient: AccountId,
+        memo: Option<String>,
+    ) {
+        let initial_storage_usage = env::storage_usage();
+        let sender_id = env::predecessor_account_id();
+        require!(
+            fee_bps < BASIS_POINTS_DENOMINATOR,
+            "fee_bps must be below 10000"
+        );
+        self.internal_require_registered(&fee_recipient);
+        let total = self.internal_validate_batch(&sender_id, &transfers);
+
+        self.token.internal_withdraw(&sender_id, total);
+        let mut total_fee: Balance = 0;
+        let mut legs: Vec<(&AccountId, U128, Option<&str>)> =
+            Vec::with_capacity(transfers.len() + 1);
+        for (receiver_id, amount) in transfers.iter() {
+            let amount: Balance = (*amount).into();
+            let fee = amount * Balance::from(fee_bps) / Balance::from(BASIS_POINTS_DENOMINATOR);
+            let net = amount - fee;
+            total_fee += fee;
+            self.token.internal_deposit(receiver_id, net);
+            legs.push((receiver_id, net.into(), memo.as_deref()));
+            let receiver_balance = self.token.accounts.get(receiver_id).unwrap_or(0);
+            self.internal_record_balance_checkpoint(receiver_id, receiver_balance);
+        }
+        if total_fee > 0 {
+            self.token.internal_deposit(&fee_recipient, total_fee);
+            legs.push((&fee_recipient, total_fee.into(), Some("Batch transfer fee")));
+            let fee_recipient_balance = self.token.accounts.get(&fee_recipient).unwrap_or(0);
+            self.internal_record_balance_checkpoint(&fee_recipient, fee_recipient_balance);
+        }
+        let events: Vec<FtTransfer> = legs
+            .iter()
+            .map(|(receiver_id, amount, memo)| FtTransfer {
+                old_owner_id: &sender_id,
+                new_owner_id: receiver_id,
+                amount,
+                memo: *memo,
+            })
+            .collect();
+        FtTransfer::emit_many(&events);
+
+        let sender_balance = self.token.accounts.get(&sender_id).unwrap_or(0);
+        self.internal_record_balance_checkpoint(&sender_id, sender_balance);
+        log!(
+            "@{} batch transferred {} total ({} fee) across {} receivers",
+            sender_id,
+            total,
+            total_fee,
+            transfers.len()
+        );
+        self.internal_charge_storage_growth(initial_storage_usage);
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_contract_standards::fungible_token::core::FungibleTokenCore;
+    use near_contract_standards::storage_management::StorageManagement;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::MockedBlockchain;
+    use near_sdk::testing_env;
+
+    use super::*;
+
+    const TOTAL_SUPPLY: Balance = 1_000_000_000_000_000;
+    /// 0.01 NEAR: covers the checkpoint logs a batch creates for its new receivers (roughly 200
+    /// bytes each), which the bare registration minimum does not.
+    const BATCH_STORAGE_DEPOSIT: Balance = 10_000_000_000_000_000_000_000;
+
+    fn get_context(predecessor_account_id: AccountId) -> VMContextBuilder {
+        let mut builder = VMContextBuilder::new();
+        builder
+            .current_account_id(accounts(0))
+            .signer_account_id(predecessor_account_id.clone())
+            .predecessor_account_id(predecessor_account_id);
+        builder
+    }
+
+    /// `accounts(0)` is both the contract's own account and the token owner, so it holds the full
+    /// supply to batch-send from without a separate funding step.
+    fn setup() -> (Contract, VMContextBuilder) {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let contract = Contract::new_default_meta(accounts(0).into(), TOTAL_SUPPLY.into());
+        (contract, context)
+    }
+
+    fn register(contract: &mut Contract, context: &mut VMContextBuilder, account_id: AccountId) {
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(contract.storage_balance_bounds().min.into())
+            .predecessor_account_id(account_id)
+            .build());
+        contract.storage_deposit(None, None);
+    }
+
+    #[test]
+    fn test_ft_transfer_batch_credits_every_receiver() {
+        let (mut contract, mut context) = setup();
+        register(&mut contract, &mut context, accounts(1));
+        register(&mut contract, &mut context, accounts(2));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(BATCH_STORAGE_DEPOSIT)
+            .predecessor_account_id(accounts(0))
+            .build());
+        contract.ft_transfer_batch(
+            vec![(accounts(1), 100.into()), (accounts(2), 200.into())],
+            None,
+        );
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .is_view(true)
+            .attached_deposit(0)
+            .build());
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 100);
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, 200);
+        assert_eq!(contract.ft_balance_of(accounts(0)).0, TOTAL_SUPPLY - 300);
+    }
+
+    #[test]
+    #[should_panic(expected = "Must attach at least")]
+    fn test_ft_transfer_batch_requires_deposit_to_cover_checkpoint_growth() {
+        let (mut contract, mut context) = setup();
+        register(&mut contract, &mut context, accounts(1));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(0)
+            .predecessor_account_id(accounts(0))
+            .build());
+        contract.ft_transfer_batch(vec![(accounts(1), 100.into())], None);
+    }
+
+    #[test]
+    fn test_ft_transfer_batch_with_fee_splits_to_fee_recipient() {
+        let (mut contract, mut context) = setup();
+        register(&mut contract, &mut context, accounts(1));
+        register(&mut contract, &mut context, accounts(2));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(BATCH_STORAGE_DEPOSIT)
+            .predecessor_account_id(accounts(0))
+            .build());
+        // 1000 at a 1000 bps (10%) fee leaves 900 for the receiver and 100 for the fee recipient.
+        contract.ft_transfer_batch_with_fee(
+            vec![(accounts(1), 1_000.into())],
+            1_000,
+            accounts(2),
+            None,
+        );
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .is_view(true)
+            .attached_deposit(0)
+            .build());
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 900);
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, 100);
+    }
+}
@@ -15,20 +15,91 @@ NOTES:
   - To prevent the deployed contract from being modified or deleted, it should not have any access
     keys on its account.
 */
+mod amm;
+mod balance_snapshot;
+mod batch_transfer;
+mod math;
+mod referral;
+mod staking;
+mod vesting;
+
+use near_contract_standards::fungible_token::core::FungibleTokenCore;
 use near_contract_standards::fungible_token::metadata::{
     FungibleTokenMetadata, FungibleTokenMetadataProvider, FT_METADATA_SPEC,
 };
+use near_contract_standards::fungible_token::resolver::FungibleTokenResolver;
 use near_contract_standards::fungible_token::FungibleToken;
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::collections::LazyOption;
+use near_sdk::collections::{LazyOption, LookupMap, UnorderedSet, Vector};
 use near_sdk::json_types::U128;
-use near_sdk::{env, log, near_bindgen, AccountId, Balance, PanicOnDefault, PromiseOrValue};
+use near_sdk::{
+    env, log, near_bindgen, require, AccountId, Balance, BorshStorageKey, PanicOnDefault,
+    PromiseOrValue,
+};
+
+use crate::balance_snapshot::{Checkpoint, DEFAULT_CHECKPOINT_RETENTION_BLOCKS};
+use crate::staking::StakerInfo;
+use crate::vesting::VestingGrant;
 
 #[near_bindgen]
 #[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
 pub struct Contract {
     token: FungibleToken,
     metadata: LazyOption<FungibleTokenMetadata>,
+    owner_id: AccountId,
+    /// Append-only per-account history of `(block_height, balance)` checkpoints, used to answer
+    /// `ft_balance_of_at`. See [`balance_snapshot`] for the write/read paths.
+    balance_checkpoints: LookupMap<AccountId, Vector<Checkpoint>>,
+    /// Append-only `(block_height, total_supply)` checkpoints, used to answer `ft_total_supply_at`.
+    supply_checkpoints: Vector<Checkpoint>,
+    /// Checkpoints older than this many blocks may be pruned on the next write. Defaults to
+    /// [`balance_snapshot::DEFAULT_CHECKPOINT_RETENTION_BLOCKS`] so that plain transfers, which
+    /// can't be charged for the checkpoint they write the way `vest`/`stake`/`add_liquidity` are,
+    /// don't grow this map without bound. `None` disables pruning (unbounded history).
+    checkpoint_retention_blocks: Option<u64>,
+    /// Accounts allowed to create vesting grants in addition to `owner_id`.
+    authorized_granters: UnorderedSet<AccountId>,
+    /// Per-beneficiary vesting schedules, in grant order.
+    vesting_grants: LookupMap<AccountId, Vec<VestingGrant>>,
+    /// Sum of `total - released` across every outstanding vesting grant. These tokens sit in the
+    /// contract account's own FT balance (escrowed there by `vest`) and are excluded from its
+    /// spendable balance by `ft_available_balance_of`.
+    vesting_locked_total: Balance,
+    /// Upper bound, in basis points, on the combined referral cut `ft_transfer_with_referral` may
+    /// deduct from a transfer.
+    max_referral_bps: u16,
+    /// Token side of the token/NEAR constant-product pool.
+    amm_reserve_token: Balance,
+    /// NEAR side of the token/NEAR constant-product pool.
+    amm_reserve_near: Balance,
+    /// LP share units per holder.
+    amm_lp_shares: LookupMap<AccountId, Balance>,
+    /// Sum of every holder's LP shares.
+    amm_total_lp_shares: Balance,
+    /// Swap fee, in basis points, owner-configurable.
+    amm_fee_bps: u16,
+    /// Token amounts deposited via `ft_transfer_call` (msg `"add_liquidity"`) awaiting the
+    /// matching `add_liquidity` call that attaches the NEAR side.
+    amm_pending_token_deposits: LookupMap<AccountId, Balance>,
+    /// Sum of every account's staked balance. These tokens sit in the contract account's own FT
+    /// balance (escrowed there by `stake`) and are excluded from its spendable balance.
+    staking_total_staked: Balance,
+    /// Cumulative reward per staked token, scaled by `ACC_PRECISION`. Only ever grows.
+    staking_acc_reward_per_share: u128,
+    /// Per-account stake and reward-debt bookkeeping. See [`staking`] for the accumulator math.
+    staking_accounts: LookupMap<AccountId, StakerInfo>,
+}
+
+#[derive(BorshSerialize, BorshStorageKey)]
+pub(crate) enum StorageKey {
+    BalanceCheckpoints,
+    BalanceCheckpointsInner { account_hash: Vec<u8> },
+    SupplyCheckpoints,
+    AuthorizedGranters,
+    VestingGrants,
+    AmmLpShares,
+    AmmPendingTokenDeposits,
+    StakingAccounts,
 }
 
 // const DATA_IMAGE_SVG_NEAR_ICON: &str = "data:image/svg+xml,%3Csvg xmlns='http://www.w3.org/2000/svg' viewBox='0 0 288 288'%3E%3Cg id='l' data-name='l'%3E%3Cpath d='M187.58,79.81l-30.1,44.69a3.2,3.2,0,0,0,4.75,4.2L191.86,103a1.2,1.2,0,0,1,2,.91v80.46a1.2,1.2,0,0,1-2.12.77L102.18,77.93A15.35,15.35,0,0,0,90.47,72.5H87.34A15.34,15.34,0,0,0,72,87.84V201.16A15.34,15.34,0,0,0,87.34,216.5h0a15.35,15.35,0,0,0,13.08-7.31l30.1-44.69a3.2,3.2,0,0,0-4.75-4.2L96.14,186a1.2,1.2,0,0,1-2-.91V104.61a1.2,1.2,0,0,1,2.12-.77l89.55,107.23a15.35,15.35,0,0,0,11.71,5.43h3.13A15.34,15.34,0,0,0,216,201.16V87.84A15.34,15.34,0,0,0,200.66,72.5h0A15.35,15.35,0,0,0,187.58,79.81Z'/%3E%3C/g%3E%3C/svg%3E";
@@ -58,19 +129,41 @@ impl Contract {
     /// Initializes the contract with the given total supply owned by the given `owner_id` with
     /// the given fungible token metadata.
     #[init]
-    pub fn new(
-        owner_id: AccountId,
-        total_supply: U128,
-        metadata: FungibleTokenMetadata,
-    ) -> Self {
+    pub fn new(owner_id: AccountId, total_supply: U128, metadata: FungibleTokenMetadata) -> Self {
         assert!(!env::state_exists(), "Already initialized");
         metadata.assert_valid();
         let mut this = Self {
             token: FungibleToken::new(b"a".to_vec()),
             metadata: LazyOption::new(b"m".to_vec(), Some(&metadata)),
+            owner_id: owner_id.clone(),
+            balance_checkpoints: LookupMap::new(StorageKey::BalanceCheckpoints),
+            supply_checkpoints: Vector::new(StorageKey::SupplyCheckpoints),
+            checkpoint_retention_blocks: Some(DEFAULT_CHECKPOINT_RETENTION_BLOCKS),
+            authorized_granters: UnorderedSet::new(StorageKey::AuthorizedGranters),
+            vesting_grants: LookupMap::new(StorageKey::VestingGrants),
+            vesting_locked_total: 0,
+            max_referral_bps: 1_000,
+            amm_reserve_token: 0,
+            amm_reserve_near: 0,
+            amm_lp_shares: LookupMap::new(StorageKey::AmmLpShares),
+            amm_total_lp_shares: 0,
+            amm_fee_bps: 30,
+            amm_pending_token_deposits: LookupMap::new(StorageKey::AmmPendingTokenDeposits),
+            staking_total_staked: 0,
+            staking_acc_reward_per_share: 0,
+            staking_accounts: LookupMap::new(StorageKey::StakingAccounts),
         };
         this.token.internal_register_account(&owner_id);
+        // Every escrow-based subsystem (vesting, staking, the AMM) parks tokens on the contract's
+        // own account, so it needs to be a registered FT holder from the start -- unless that
+        // happens to be `owner_id` too, in which case it already just was.
+        let contract_account_id = env::current_account_id();
+        if contract_account_id != owner_id {
+            this.token.internal_register_account(&contract_account_id);
+        }
         this.token.internal_deposit(&owner_id, total_supply.into());
+        this.internal_record_balance_checkpoint(&owner_id, total_supply.into());
+        this.internal_record_supply_checkpoint();
         near_contract_standards::fungible_token::events::FtMint {
             owner_id: &owner_id,
             amount: &total_supply,
@@ -86,10 +179,95 @@ impl Contract {
 
     fn on_tokens_burned(&mut self, account_id: AccountId, amount: Balance) {
         log!("Account @{} burned {}", account_id, amount);
+        let balance = self.token.accounts.get(&account_id).unwrap_or(0);
+        self.internal_record_balance_checkpoint(&account_id, balance);
+        self.internal_record_supply_checkpoint();
+    }
+
+    /// Panics unless `account_id` has already paid the NEP-145 storage deposit for this token,
+    /// i.e. has a registered FT balance. Reused by every subsystem that deposits into an
+    /// arbitrary account (vesting, referrals, the AMM, batch transfer).
+    pub(crate) fn internal_require_registered(&self, account_id: &AccountId) {
+        require!(
+            self.token.accounts.contains_key(account_id),
+            format!("The account {} is not registered", account_id)
+        );
+    }
+
+    /// Charges `env::attached_deposit()` against the storage consumed since `initial_storage`,
+    /// refunding the remainder to the predecessor. Used by the payable subsystem methods (vesting,
+    /// staking, the AMM, ...) whose state growth isn't covered by the NEP-141 storage-deposit
+    /// registration, so it's billed the same way the contract already bills storage elsewhere:
+    /// measure the delta, require it's covered, refund the rest.
+    pub(crate) fn internal_charge_storage_growth(&self, initial_storage: u64) {
+        let storage_used = env::storage_usage().saturating_sub(initial_storage);
+        let required_cost = Balance::from(storage_used) * env::storage_byte_cost();
+        let attached = env::attached_deposit();
+        assert!(
+            attached >= required_cost,
+            "Must attach at least {} yoctoNEAR to cover storage",
+            required_cost
+        );
+        let refund = attached - required_cost;
+        if refund > 0 {
+            near_sdk::Promise::new(env::predecessor_account_id()).transfer(refund);
+        }
+    }
+}
+
+// Not `impl_fungible_token_core!`: that macro forwards straight to `self.token`, and these are
+// the only two entry points through which an ordinary transfer ever changes a balance, so the
+// checkpoint log (see `balance_snapshot`) needs a hook on both of them and on the resolver below.
+#[near_bindgen]
+impl FungibleTokenCore for Contract {
+    fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>) {
+        let sender_id = env::predecessor_account_id();
+        self.token.ft_transfer(receiver_id.clone(), amount, memo);
+        self.internal_record_transfer_checkpoints(&sender_id, &receiver_id);
+    }
+
+    fn ft_transfer_call(
+        &mut self,
+        receiver_id: AccountId,
+        amount: U128,
+        memo: Option<String>,
+        msg: String,
+    ) -> PromiseOrValue<U128> {
+        let sender_id = env::predecessor_account_id();
+        let result = self.token.ft_transfer_call(receiver_id.clone(), amount, memo, msg);
+        self.internal_record_transfer_checkpoints(&sender_id, &receiver_id);
+        result
+    }
+
+    fn ft_total_supply(&self) -> U128 {
+        self.token.ft_total_supply()
+    }
+
+    fn ft_balance_of(&self, account_id: AccountId) -> U128 {
+        self.token.ft_balance_of(account_id)
+    }
+}
+
+#[near_bindgen]
+impl FungibleTokenResolver for Contract {
+    #[private]
+    fn ft_resolve_transfer(
+        &mut self,
+        sender_id: AccountId,
+        receiver_id: AccountId,
+        amount: U128,
+    ) -> U128 {
+        let (used_amount, burned_amount) =
+            self.token
+                .internal_ft_resolve_transfer(&sender_id, receiver_id.clone(), amount);
+        if burned_amount > 0 {
+            self.on_tokens_burned(sender_id.clone(), burned_amount);
+        }
+        self.internal_record_transfer_checkpoints(&sender_id, &receiver_id);
+        used_amount.into()
     }
 }
 
-near_contract_standards::impl_fungible_token_core!(Contract, token, on_tokens_burned);
 near_contract_standards::impl_fungible_token_storage!(Contract, token, on_account_closed);
 
 #[near_bindgen]
@@ -163,7 +341,10 @@ mod tests {
             .is_view(true)
             .attached_deposit(0)
             .build());
-        assert_eq!(contract.ft_balance_of(accounts(2)).0, (TOTAL_SUPPLY - transfer_amount));
+        assert_eq!(
+            contract.ft_balance_of(accounts(2)).0,
+            (TOTAL_SUPPLY - transfer_amount)
+        );
         assert_eq!(contract.ft_balance_of(accounts(1)).0, transfer_amount);
     }
 }
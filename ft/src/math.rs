@@ -0,0 +1,46 @@
+//! Wide-integer helpers for reserve math. Token amounts and yoctoNEAR amounts both use 24
+//! decimals, so even modest AMM deposits make a plain `Balance * Balance` multiply overflow u128
+//! (e.g. `1e24 * 1e24 = 1e48`, far past `u128::MAX`'s ~3.4e38). Every reserve/share calculation
+//! that multiplies two reserve-scale amounts before dividing should go through here instead of
+//! multiplying directly.
+
+use near_sdk::{env, Balance};
+use uint::construct_uint;
+
+construct_uint! {
+    pub struct U256(4);
+}
+
+/// Computes `a * b / c`, carrying the intermediate product in 256 bits so it can't silently wrap.
+/// Panics if the final result doesn't fit back into a `Balance`.
+pub fn mul_div(a: Balance, b: Balance, c: Balance) -> Balance {
+    let result = U256::from(a) * U256::from(b) / U256::from(c);
+    if result.bits() > 128 {
+        env::panic_str("Overflow in pool calculation");
+    }
+    result.as_u128()
+}
+
+/// Computes `floor(sqrt(a * b))`, carrying the intermediate product in 256 bits so it can't
+/// silently wrap. Panics if the final result doesn't fit back into a `Balance`.
+pub fn sqrt_mul(a: Balance, b: Balance) -> Balance {
+    let product = U256::from(a) * U256::from(b);
+    let root = integer_sqrt(product);
+    if root.bits() > 128 {
+        env::panic_str("Overflow in pool calculation");
+    }
+    root.as_u128()
+}
+
+fn integer_sqrt(value: U256) -> U256 {
+    if value.is_zero() {
+        return U256::zero();
+    }
+    let mut x = value;
+    let mut y = (x + U256::one()) / 2;
+    while y < x {
+        x = y;
+        y = (x + value / x) / 2;
+    }
+    x
+}
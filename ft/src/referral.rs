@@ -0,0 +1,202 @@
+//! Single transfer fanning out a cut to multiple referral accounts, basis-points style.
+
+use near_contract_standards::fungible_token::events::FtTransfer;
+use near_sdk::json_types::U128;
+use near_sdk::{assert_one_yocto, env, near_bindgen, require, AccountId, Balance};
+
+use crate::Contract;
+
+const BASIS_POINTS_DENOMINATOR: u16 = 10_000;
+
+#[near_bindgen]
+impl Contract {
+    /// Owner-only: caps the combined basis points `ft_transfer_with_referral` may deduct.
+    #[payable]
+    pub fn set_max_referral_bps(&mut self, max_referral_bps: u16) {
+        assert_one_yocto();
+        require!(
+            env::predecessor_account_id() == self.owner_id,
+            "Only the owner can set the max referral bps"
+        );
+        require!(
+            max_referral_bps <= BASIS_POINTS_DENOMINATOR,
+            "max_referral_bps cannot exceed 10000"
+        );
+        self.max_referral_bps = max_referral_bps;
+    }
+
+    /// Transfers `amount` from the caller to `receiver_id`, deducting a basis-point cut for each
+    /// `(account, bps)` pair in `referrals` (combined bps must be `<= max_referral_bps`) and
+    /// crediting the remainder, plus any flooring dust, to `receiver_id`. Every leg emits its own
+    /// `FtTransfer` event so indexers can attribute the splits.
+    #[payable]
+    pub fn ft_transfer_with_referral(
+        &mut self,
+        receiver_id: AccountId,
+        amount: U128,
+        referrals: Vec<(AccountId, u16)>,
+        memo: Option<String>,
+    ) {
+        assert_one_yocto();
+        let sender_id = env::predecessor_account_id();
+        let amount: Balance = amount.into();
+        require!(
+            amount > 0,
+            "The transfer amount should be a positive number"
+        );
+        require!(
+            receiver_id != sender_id,
+            "Sender and receiver should be different"
+        );
+        self.internal_require_registered(&receiver_id);
+
+        let total_bps: u32 = referrals.iter().map(|(_, bps)| *bps as u32).sum();
+        require!(
+            total_bps <= self.max_referral_bps as u32,
+            "Combined referral bps exceeds max_referral_bps"
+        );
+
+        self.token.internal_withdraw(&sender_id, amount);
+
+        let mut distributed: Balance = 0;
+        for (referral_id, bps) in referrals.iter() {
+            require!(referral_id != &sender_id, "A referral cannot be the sender");
+            self.internal_require_registered(referral_id);
+            let cut = amount * Balance::from(*bps) / Balance::from(BASIS_POINTS_DENOMINATOR);
+            if cut == 0 {
+                continue;
+            }
+            self.token.internal_deposit(referral_id, cut);
+            distributed += cut;
+            FtTransfer {
+                old_owner_id: &sender_id,
+                new_owner_id: referral_id,
+                amount: &cut.into(),
+                memo: Some("Referral payout"),
+            }
+            .emit();
+            let referral_balance = self.token.accounts.get(referral_id).unwrap_or(0);
+            self.internal_record_balance_checkpoint(referral_id, referral_balance);
+        }
+
+        // Any rounding dust from flooring each referral's share goes to the receiver, so the sum
+        // of all legs is exactly `amount` and no tokens are created or destroyed.
+        let receiver_amount = amount - distributed;
+        self.token.internal_deposit(&receiver_id, receiver_amount);
+        FtTransfer {
+            old_owner_id: &sender_id,
+            new_owner_id: &receiver_id,
+            amount: &receiver_amount.into(),
+            memo: memo.as_deref().or(Some("Referral payout")),
+        }
+        .emit();
+
+        let sender_balance = self.token.accounts.get(&sender_id).unwrap_or(0);
+        let receiver_balance = self.token.accounts.get(&receiver_id).unwrap_or(0);
+        self.internal_record_balance_checkpoint(&sender_id, sender_balance);
+        self.internal_record_balance_checkpoint(&receiver_id, receiver_balance);
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_contract_standards::fungible_token::core::FungibleTokenCore;
+    use near_contract_standards::storage_management::StorageManagement;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::MockedBlockchain;
+    use near_sdk::testing_env;
+
+    use super::*;
+
+    const TOTAL_SUPPLY: Balance = 1_000_000_000_000_000;
+
+    fn get_context(predecessor_account_id: AccountId) -> VMContextBuilder {
+        let mut builder = VMContextBuilder::new();
+        builder
+            .current_account_id(accounts(0))
+            .signer_account_id(predecessor_account_id.clone())
+            .predecessor_account_id(predecessor_account_id);
+        builder
+    }
+
+    fn setup() -> (Contract, VMContextBuilder) {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let contract = Contract::new_default_meta(accounts(0).into(), TOTAL_SUPPLY.into());
+        (contract, context)
+    }
+
+    fn register_and_fund(
+        contract: &mut Contract,
+        context: &mut VMContextBuilder,
+        account_id: AccountId,
+        amount: Balance,
+    ) {
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(contract.storage_balance_bounds().min.into())
+            .predecessor_account_id(account_id.clone())
+            .build());
+        contract.storage_deposit(None, None);
+        // NEP-141 rejects zero-amount transfers, so a zero `amount` only registers the account.
+        if amount == 0 {
+            return;
+        }
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(0))
+            .build());
+        contract.ft_transfer(account_id, amount.into(), None);
+    }
+
+    #[test]
+    fn test_referral_split_with_rounding_dust_to_receiver() {
+        let (mut contract, mut context) = setup();
+        register_and_fund(&mut contract, &mut context, accounts(1), 1_000);
+        register_and_fund(&mut contract, &mut context, accounts(2), 0);
+        register_and_fund(&mut contract, &mut context, accounts(3), 0);
+        register_and_fund(&mut contract, &mut context, accounts(4), 0);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(1))
+            .build());
+        // 999 split 30%/20% floors to 299/199, leaving 1 yoctotoken of dust for the receiver.
+        contract.ft_transfer_with_referral(
+            accounts(2),
+            999.into(),
+            vec![(accounts(3), 3_000), (accounts(4), 2_000)],
+            None,
+        );
+
+        testing_env!(context.storage_usage(env::storage_usage()).is_view(true).build());
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 1);
+        assert_eq!(contract.ft_balance_of(accounts(3)).0, 299);
+        assert_eq!(contract.ft_balance_of(accounts(4)).0, 199);
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, 999 - 299 - 199);
+    }
+
+    #[test]
+    #[should_panic(expected = "Combined referral bps exceeds max_referral_bps")]
+    fn test_referral_rejects_bps_over_the_configured_max() {
+        let (mut contract, mut context) = setup();
+        register_and_fund(&mut contract, &mut context, accounts(1), 1_000);
+        register_and_fund(&mut contract, &mut context, accounts(2), 0);
+        register_and_fund(&mut contract, &mut context, accounts(3), 0);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.ft_transfer_with_referral(
+            accounts(2),
+            1_000.into(),
+            vec![(accounts(3), contract.max_referral_bps + 1)],
+            None,
+        );
+    }
+}
@@ -0,0 +1,332 @@
+//! Single-pool staking with O(1) proportional reward distribution.
+//!
+//! Rather than iterating every staker on each `distribute_rewards` call, this follows the
+//! standard accumulator ("MasterChef-style") algorithm: `staking_acc_reward_per_share` tracks the
+//! cumulative reward earned per staked token, scaled by [`ACC_PRECISION`] to match the token's 24
+//! decimals, and only ever grows. Each account's [`StakerInfo::reward_debt`] is the portion of
+//! `staked * acc_reward_per_share` already paid out or already staked before the last reward ran,
+//! so `staked * acc_reward_per_share - reward_debt` is exactly the pending, unclaimed reward.
+//! `stake`/`unstake`/`claim` all settle this pending amount (crediting it to the caller's FT
+//! balance) before changing `staked` and recomputing `reward_debt`.
+
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::{assert_one_yocto, env, log, near_bindgen, require, AccountId, Balance};
+
+use crate::math::mul_div;
+use crate::Contract;
+
+/// Scale factor for `acc_reward_per_share`, matching the token's 24 decimals so a reward of a
+/// single yoctotoken per block still accrues without rounding to zero.
+const ACC_PRECISION: u128 = 1_000_000_000_000_000_000_000_000;
+
+#[derive(BorshDeserialize, BorshSerialize, Clone, Default)]
+pub struct StakerInfo {
+    pub staked: Balance,
+    pub reward_debt: u128,
+}
+
+impl Contract {
+    fn internal_pending_reward(&self, info: &StakerInfo) -> Balance {
+        let accrued = mul_div(info.staked, self.staking_acc_reward_per_share, ACC_PRECISION);
+        accrued.saturating_sub(info.reward_debt)
+    }
+
+    /// Settles `account_id`'s pending reward (crediting their FT balance) and returns their
+    /// up-to-date [`StakerInfo`], still holding its pre-settlement `staked` amount.
+    fn internal_settle_reward(&mut self, account_id: &AccountId) -> StakerInfo {
+        let mut info = self.staking_accounts.get(account_id).unwrap_or_default();
+        let pending = self.internal_pending_reward(&info);
+        if pending > 0 {
+            let escrow_id = env::current_account_id();
+            self.token.internal_withdraw(&escrow_id, pending);
+            self.token.internal_deposit(account_id, pending);
+            let escrow_balance = self.token.accounts.get(&escrow_id).unwrap_or(0);
+            let account_balance = self.token.accounts.get(account_id).unwrap_or(0);
+            self.internal_record_balance_checkpoint(&escrow_id, escrow_balance);
+            self.internal_record_balance_checkpoint(account_id, account_balance);
+            log!("@{} claimed {} staking reward", account_id, pending);
+        }
+        info.reward_debt = mul_div(info.staked, self.staking_acc_reward_per_share, ACC_PRECISION);
+        info
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Escrows `amount` out of the caller's balance into the staking pool, first settling any
+    /// reward already pending on their existing stake.
+    #[payable]
+    pub fn stake(&mut self, amount: U128) -> U128 {
+        let initial_storage_usage = env::storage_usage();
+        let account_id = env::predecessor_account_id();
+        let amount: Balance = amount.into();
+        require!(amount > 0, "The stake amount should be a positive number");
+
+        let mut info = self.internal_settle_reward(&account_id);
+
+        let escrow_id = env::current_account_id();
+        self.token.internal_withdraw(&account_id, amount);
+        self.token.internal_deposit(&escrow_id, amount);
+
+        info.staked += amount;
+        info.reward_debt = mul_div(info.staked, self.staking_acc_reward_per_share, ACC_PRECISION);
+        self.staking_accounts.insert(&account_id, &info);
+        self.staking_total_staked += amount;
+
+        let account_balance = self.token.accounts.get(&account_id).unwrap_or(0);
+        let escrow_balance = self.token.accounts.get(&escrow_id).unwrap_or(0);
+        self.internal_record_balance_checkpoint(&account_id, account_balance);
+        self.internal_record_balance_checkpoint(&escrow_id, escrow_balance);
+
+        log!(
+            "@{} staked {}, total staked {}",
+            account_id,
+            amount,
+            info.staked
+        );
+        self.internal_charge_storage_growth(initial_storage_usage);
+        info.staked.into()
+    }
+
+    /// Settles the caller's pending reward and withdraws `amount` of their stake back to their FT
+    /// balance.
+    #[payable]
+    pub fn unstake(&mut self, amount: U128) -> U128 {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let amount: Balance = amount.into();
+        require!(amount > 0, "The unstake amount should be a positive number");
+
+        let mut info = self.internal_settle_reward(&account_id);
+        require!(amount <= info.staked, "Not enough staked balance");
+
+        let escrow_id = env::current_account_id();
+        self.token.internal_withdraw(&escrow_id, amount);
+        self.token.internal_deposit(&account_id, amount);
+
+        info.staked -= amount;
+        info.reward_debt = mul_div(info.staked, self.staking_acc_reward_per_share, ACC_PRECISION);
+        self.staking_accounts.insert(&account_id, &info);
+        self.staking_total_staked -= amount;
+
+        let account_balance = self.token.accounts.get(&account_id).unwrap_or(0);
+        let escrow_balance = self.token.accounts.get(&escrow_id).unwrap_or(0);
+        self.internal_record_balance_checkpoint(&account_id, account_balance);
+        self.internal_record_balance_checkpoint(&escrow_id, escrow_balance);
+
+        log!(
+            "@{} unstaked {}, remaining staked {}",
+            account_id,
+            amount,
+            info.staked
+        );
+        info.staked.into()
+    }
+
+    /// Settles and pays out the caller's pending reward without changing their staked amount.
+    /// Returns the amount paid out.
+    pub fn claim(&mut self) -> U128 {
+        let account_id = env::predecessor_account_id();
+        let pending = self.pending_reward(account_id.clone());
+        let info = self.internal_settle_reward(&account_id);
+        self.staking_accounts.insert(&account_id, &info);
+        pending
+    }
+
+    /// Owner-only: funds `amount` of rewards out of its own FT balance into the pool, bumping
+    /// `acc_reward_per_share` proportionally across every current staker. Panics if nobody is
+    /// staked (there would be nobody to receive the reward).
+    #[payable]
+    pub fn distribute_rewards(&mut self, amount: U128) {
+        assert_one_yocto();
+        require!(
+            env::predecessor_account_id() == self.owner_id,
+            "Only the owner can distribute staking rewards"
+        );
+        let amount: Balance = amount.into();
+        require!(amount > 0, "The reward amount should be a positive number");
+        require!(self.staking_total_staked > 0, "No staked tokens to reward");
+
+        let owner_id = self.owner_id.clone();
+        let escrow_id = env::current_account_id();
+        self.token.internal_withdraw(&owner_id, amount);
+        self.token.internal_deposit(&escrow_id, amount);
+        self.staking_acc_reward_per_share +=
+            mul_div(amount, ACC_PRECISION, self.staking_total_staked);
+
+        let owner_balance = self.token.accounts.get(&owner_id).unwrap_or(0);
+        let escrow_balance = self.token.accounts.get(&escrow_id).unwrap_or(0);
+        self.internal_record_balance_checkpoint(&owner_id, owner_balance);
+        self.internal_record_balance_checkpoint(&escrow_id, escrow_balance);
+
+        log!(
+            "Distributed {} staking reward across {} staked",
+            amount,
+            self.staking_total_staked
+        );
+    }
+
+    /// The reward `account_id` could claim right now without changing their stake.
+    pub fn pending_reward(&self, account_id: AccountId) -> U128 {
+        let info = self.staking_accounts.get(&account_id).unwrap_or_default();
+        self.internal_pending_reward(&info).into()
+    }
+
+    /// The account's currently staked balance.
+    pub fn staked_balance_of(&self, account_id: AccountId) -> U128 {
+        self.staking_accounts
+            .get(&account_id)
+            .unwrap_or_default()
+            .staked
+            .into()
+    }
+
+    /// The sum of every account's staked balance.
+    pub fn total_staked(&self) -> U128 {
+        self.staking_total_staked.into()
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_contract_standards::fungible_token::core::FungibleTokenCore;
+    use near_contract_standards::storage_management::StorageManagement;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::MockedBlockchain;
+    use near_sdk::testing_env;
+
+    use super::*;
+
+    const TOTAL_SUPPLY: Balance = 1_000_000_000_000_000;
+
+    fn get_context(predecessor_account_id: AccountId) -> VMContextBuilder {
+        let mut builder = VMContextBuilder::new();
+        builder
+            .current_account_id(accounts(0))
+            .signer_account_id(predecessor_account_id.clone())
+            .predecessor_account_id(predecessor_account_id);
+        builder
+    }
+
+    /// `accounts(0)` is both the contract's own account and the token owner, so it doubles as the
+    /// staking pool's escrow without a separate registration step.
+    fn setup() -> (Contract, VMContextBuilder) {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let contract = Contract::new_default_meta(accounts(0).into(), TOTAL_SUPPLY.into());
+        (contract, context)
+    }
+
+    fn register_and_fund(
+        contract: &mut Contract,
+        context: &mut VMContextBuilder,
+        account_id: AccountId,
+        amount: Balance,
+    ) {
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(contract.storage_balance_bounds().min.into())
+            .predecessor_account_id(account_id.clone())
+            .build());
+        contract.storage_deposit(None, None);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(0))
+            .build());
+        contract.ft_transfer(account_id, amount.into(), None);
+    }
+
+    fn stake_as(
+        contract: &mut Contract,
+        context: &mut VMContextBuilder,
+        account_id: AccountId,
+        amount: Balance,
+    ) {
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(contract.storage_balance_bounds().min.into())
+            .predecessor_account_id(account_id)
+            .build());
+        contract.stake(amount.into());
+    }
+
+    #[test]
+    fn test_stake_distribute_unstake_multiple_stakers() {
+        let (mut contract, mut context) = setup();
+        register_and_fund(&mut contract, &mut context, accounts(1), 1_000);
+        register_and_fund(&mut contract, &mut context, accounts(2), 1_000);
+
+        // Only accounts(1) is staked when the first reward lands, so it takes the whole thing.
+        stake_as(&mut contract, &mut context, accounts(1), 100);
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(0))
+            .build());
+        contract.distribute_rewards(50.into());
+        assert_eq!(contract.pending_reward(accounts(1)).0, 50);
+
+        // accounts(2) joins after that reward, so the accumulator it's credited against already
+        // reflects it -- it must not retroactively share in the first distribution.
+        stake_as(&mut contract, &mut context, accounts(2), 100);
+        assert_eq!(contract.pending_reward(accounts(2)).0, 0);
+
+        // The second reward is split evenly between the two now-equal stakes.
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(0))
+            .build());
+        contract.distribute_rewards(100.into());
+        assert_eq!(contract.pending_reward(accounts(1)).0, 100);
+        assert_eq!(contract.pending_reward(accounts(2)).0, 50);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .predecessor_account_id(accounts(1))
+            .build());
+        assert_eq!(contract.claim().0, 100);
+        assert_eq!(contract.pending_reward(accounts(1)).0, 0);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .predecessor_account_id(accounts(2))
+            .build());
+        assert_eq!(contract.claim().0, 50);
+        assert_eq!(contract.pending_reward(accounts(2)).0, 0);
+
+        // Unstaking in full after claiming settles zero additional reward and returns exactly the
+        // staked principal.
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(1))
+            .build());
+        assert_eq!(contract.unstake(100.into()).0, 0);
+        assert_eq!(contract.staked_balance_of(accounts(1)).0, 0);
+        assert_eq!(contract.total_staked().0, 100);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .is_view(true)
+            .attached_deposit(0)
+            .build());
+        // accounts(1) started with 1000, staked 100 (escrowed), claimed 100 and unstaked 100 back.
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 1_000 + 100);
+    }
+
+    #[test]
+    #[should_panic(expected = "No staked tokens to reward")]
+    fn test_distribute_rewards_requires_a_staker() {
+        let (mut contract, mut context) = setup();
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(0))
+            .build());
+        contract.distribute_rewards(10.into());
+    }
+}
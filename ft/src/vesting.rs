@@ -0,0 +1,309 @@
+//! Linear vesting schedules with delegated ("ForTo") withdrawal.
+//!
+//! The owner (or an account it authorizes) locks tokens out of its own balance into a
+//! per-beneficiary [`VestingGrant`]. The locked amount is escrowed in the contract account's own
+//! FT balance until `withdraw_vested` releases the linearly-vested portion to the beneficiary -
+//! callable by anyone, crediting the beneficiary regardless of who pays the gas.
+
+use near_contract_standards::fungible_token::events::FtMint;
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::{U128, U64};
+use near_sdk::{assert_one_yocto, env, log, near_bindgen, require, AccountId, Balance};
+
+use crate::math::mul_div;
+use crate::Contract;
+
+#[derive(BorshDeserialize, BorshSerialize, Clone)]
+pub struct VestingGrant {
+    pub total: Balance,
+    pub released: Balance,
+    pub start: u64,
+    pub cliff: u64,
+    pub end: u64,
+}
+
+impl VestingGrant {
+    /// Amount vested (but not necessarily yet released) as of `now`: 0 before the cliff, a linear
+    /// ramp from `start` to `end`, and `total` once `end` has passed.
+    fn vested_at(&self, now: u64) -> Balance {
+        if now < self.cliff {
+            0
+        } else if now >= self.end {
+            self.total
+        } else {
+            mul_div(
+                self.total,
+                Balance::from(now - self.start),
+                Balance::from(self.end - self.start),
+            )
+        }
+    }
+
+    fn claimable_at(&self, now: u64) -> Balance {
+        self.vested_at(now).saturating_sub(self.released)
+    }
+}
+
+impl Contract {
+    fn internal_is_granter(&self, account_id: &AccountId) -> bool {
+        account_id == &self.owner_id || self.authorized_granters.contains(account_id)
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Owner-only: authorizes `granter_id` to create vesting grants via `vest`.
+    #[payable]
+    pub fn add_vesting_granter(&mut self, granter_id: AccountId) {
+        assert_one_yocto();
+        require!(
+            env::predecessor_account_id() == self.owner_id,
+            "Only the owner can add granters"
+        );
+        self.authorized_granters.insert(&granter_id);
+    }
+
+    /// Owner-only: revokes a previously authorized granter.
+    #[payable]
+    pub fn remove_vesting_granter(&mut self, granter_id: AccountId) {
+        assert_one_yocto();
+        require!(
+            env::predecessor_account_id() == self.owner_id,
+            "Only the owner can remove granters"
+        );
+        self.authorized_granters.remove(&granter_id);
+    }
+
+    /// Locks `amount` out of the caller's balance into a new linear vesting grant for
+    /// `beneficiary`, claimable between `cliff_ts` and fully vested at `end_ts`. Only the owner or
+    /// an authorized granter may call this.
+    #[payable]
+    pub fn vest(
+        &mut self,
+        beneficiary: AccountId,
+        amount: U128,
+        start_ts: U64,
+        cliff_ts: U64,
+        end_ts: U64,
+    ) {
+        let initial_storage_usage = env::storage_usage();
+        let granter = env::predecessor_account_id();
+        require!(
+            self.internal_is_granter(&granter),
+            "Not an authorized vesting granter"
+        );
+        require!(
+            cliff_ts.0 >= start_ts.0 && end_ts.0 > cliff_ts.0,
+            "Require start_ts <= cliff_ts < end_ts"
+        );
+        let amount: Balance = amount.into();
+        require!(amount > 0, "The vesting amount should be a positive number");
+
+        let escrow_id = env::current_account_id();
+        self.token.internal_withdraw(&granter, amount);
+        self.token.internal_deposit(&escrow_id, amount);
+        self.vesting_locked_total += amount;
+
+        let mut grants = self.vesting_grants.get(&beneficiary).unwrap_or_default();
+        grants.push(VestingGrant {
+            total: amount,
+            released: 0,
+            start: start_ts.0,
+            cliff: cliff_ts.0,
+            end: end_ts.0,
+        });
+        self.vesting_grants.insert(&beneficiary, &grants);
+
+        let granter_balance = self.token.accounts.get(&granter).unwrap_or(0);
+        let escrow_balance = self.token.accounts.get(&escrow_id).unwrap_or(0);
+        self.internal_record_balance_checkpoint(&granter, granter_balance);
+        self.internal_record_balance_checkpoint(&escrow_id, escrow_balance);
+
+        log!(
+            "Vested {} from @{} to @{} between {} and {}",
+            amount,
+            granter,
+            beneficiary,
+            cliff_ts.0,
+            end_ts.0
+        );
+        self.internal_charge_storage_growth(initial_storage_usage);
+    }
+
+    /// Releases the vested-but-unclaimed portion of every grant for `beneficiary`, crediting
+    /// their FT balance. Callable by anyone (the delegated "ForTo" form), not just the
+    /// beneficiary, so e.g. a relayer can cover the gas.
+    pub fn withdraw_vested(&mut self, beneficiary: AccountId) -> U128 {
+        self.internal_require_registered(&beneficiary);
+        let mut grants = self.vesting_grants.get(&beneficiary).unwrap_or_default();
+        require!(!grants.is_empty(), "No vesting grants for this account");
+
+        let now = env::block_timestamp();
+        let mut released: Balance = 0;
+        for grant in grants.iter_mut() {
+            let claimable = grant.claimable_at(now);
+            if claimable > 0 {
+                grant.released += claimable;
+                released += claimable;
+            }
+        }
+        require!(released > 0, "Nothing vested yet");
+        self.vesting_grants.insert(&beneficiary, &grants);
+
+        let escrow_id = env::current_account_id();
+        self.token.internal_withdraw(&escrow_id, released);
+        self.token.internal_deposit(&beneficiary, released);
+        self.vesting_locked_total -= released;
+
+        let escrow_balance = self.token.accounts.get(&escrow_id).unwrap_or(0);
+        let beneficiary_balance = self.token.accounts.get(&beneficiary).unwrap_or(0);
+        self.internal_record_balance_checkpoint(&escrow_id, escrow_balance);
+        self.internal_record_balance_checkpoint(&beneficiary, beneficiary_balance);
+
+        FtMint {
+            owner_id: &beneficiary,
+            amount: &released.into(),
+            memo: Some("Vesting release"),
+        }
+        .emit();
+
+        released.into()
+    }
+
+    /// The account's FT balance minus the tokens locked in unreleased vesting grants and staked
+    /// balances (only meaningful for the contract's own account, which holds both in escrow).
+    /// AMM reserves, pending `add_liquidity` deposits and unclaimed staking rewards held on that
+    /// account are not subtracted.
+    pub fn ft_available_balance_of(&self, account_id: AccountId) -> U128 {
+        let balance = self.token.accounts.get(&account_id).unwrap_or(0);
+        let locked = if account_id == env::current_account_id() {
+            self.vesting_locked_total + self.staking_total_staked
+        } else {
+            0
+        };
+        (balance - locked).into()
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_contract_standards::storage_management::StorageManagement;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::MockedBlockchain;
+    use near_sdk::testing_env;
+
+    use super::*;
+
+    const TOTAL_SUPPLY: Balance = 1_000_000_000_000_000;
+
+    fn get_context(predecessor_account_id: AccountId) -> VMContextBuilder {
+        let mut builder = VMContextBuilder::new();
+        builder
+            .current_account_id(accounts(0))
+            .signer_account_id(predecessor_account_id.clone())
+            .predecessor_account_id(predecessor_account_id);
+        builder
+    }
+
+    /// `accounts(0)` is both the contract's own account and the token owner, so it doubles as the
+    /// vesting escrow and the default granter without a separate registration or grant step.
+    fn setup() -> (Contract, VMContextBuilder) {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let contract = Contract::new_default_meta(accounts(0).into(), TOTAL_SUPPLY.into());
+        (contract, context)
+    }
+
+    fn register(contract: &mut Contract, context: &mut VMContextBuilder, account_id: AccountId) {
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(contract.storage_balance_bounds().min.into())
+            .predecessor_account_id(account_id)
+            .build());
+        contract.storage_deposit(None, None);
+    }
+
+    #[test]
+    fn test_vesting_cliff_then_linear_release() {
+        let (mut contract, mut context) = setup();
+        register(&mut contract, &mut context, accounts(1));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(contract.storage_balance_bounds().min.into())
+            .predecessor_account_id(accounts(0))
+            .block_timestamp(0)
+            .build());
+        contract.vest(accounts(1), 1_000.into(), 0.into(), 100.into(), 1_000.into());
+
+        // At the cliff, the linear ramp has reached 100 / 1000 of the total.
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .block_timestamp(100)
+            .build());
+        assert_eq!(contract.withdraw_vested(accounts(1)).0, 100);
+
+        // Halfway to `end`, another 450 has vested since the last release.
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .block_timestamp(550)
+            .build());
+        assert_eq!(contract.withdraw_vested(accounts(1)).0, 450);
+
+        // Past `end`, the remainder of the grant releases and the beneficiary holds it all.
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .block_timestamp(1_000)
+            .build());
+        assert_eq!(contract.withdraw_vested(accounts(1)).0, 450);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .is_view(true)
+            .attached_deposit(0)
+            .build());
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 1_000);
+        // Every grant has been fully released, so nothing of the escrow account's balance is
+        // locked up anymore.
+        assert_eq!(
+            contract.ft_available_balance_of(accounts(0)).0,
+            contract.ft_balance_of(accounts(0)).0
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Nothing vested yet")]
+    fn test_withdraw_vested_before_cliff_has_nothing_to_release() {
+        let (mut contract, mut context) = setup();
+        register(&mut contract, &mut context, accounts(1));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(contract.storage_balance_bounds().min.into())
+            .predecessor_account_id(accounts(0))
+            .block_timestamp(0)
+            .build());
+        contract.vest(accounts(1), 1_000.into(), 0.into(), 100.into(), 1_000.into());
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .block_timestamp(50)
+            .build());
+        contract.withdraw_vested(accounts(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "Not an authorized vesting granter")]
+    fn test_vest_requires_authorized_granter() {
+        let (mut contract, mut context) = setup();
+        register(&mut contract, &mut context, accounts(1));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(contract.storage_balance_bounds().min.into())
+            .predecessor_account_id(accounts(1))
+            .block_timestamp(0)
+            .build());
+        contract.vest(accounts(1), 1_000.into(), 0.into(), 100.into(), 1_000.into());
+    }
+}